@@ -1,3 +1,8 @@
+use std::time::Duration;
+
+use futures_util::FutureExt;
+use tokio_util::sync::CancellationToken;
+
 use crate::submit::Receiver;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
@@ -15,4 +20,44 @@ impl Null {
         }
         Ok(())
     }
+
+    /// A drain-aware counterpart of [`Null::submit_loop`]: once `stop`
+    /// fires, we stop `await`-ing fresh submissions and only resolve
+    /// whatever is already queued, for up to `grace`, so outstanding
+    /// `PromiseSender`s aren't dropped a few milliseconds from completion.
+    pub async fn submit_loop_graceful<T, U>(
+        self,
+        mut receiver: Receiver<T, U>,
+        stop: CancellationToken,
+        grace: Duration,
+    ) -> crate::Result<()>
+    where
+        U: Default,
+    {
+        loop {
+            let next = tokio::select! {
+                next = receiver.recv() => next,
+                () = stop.cancelled() => break,
+            };
+            let Some((_, reply)) = next else { break };
+            reply
+                .send(Default::default())
+                .map_err(|_| crate::err!("unexpected reply channel closing"))?
+        }
+        let deadline = tokio::time::Instant::now() + grace;
+        // Polling `recv()` through `now_or_never` rather than `await`-ing it
+        // resolves only submissions already queued; a submission that
+        // hasn't arrived yet leaves the poll pending, which `now_or_never`
+        // reports as `None` instead of waiting for it to land.
+        while tokio::time::Instant::now() < deadline {
+            let Some(next) = receiver.recv().now_or_never() else {
+                break;
+            };
+            let Some((_, reply)) = next else { break };
+            reply
+                .send(Default::default())
+                .map_err(|_| crate::err!("unexpected reply channel closing"))?
+        }
+        Ok(())
+    }
 }
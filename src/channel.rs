@@ -1,4 +1,11 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
 use derive_more::From;
+use futures_core::Stream;
+use tokio_stream::wrappers::UnboundedReceiverStream;
 
 #[derive(Debug, From)]
 pub struct EventSource<M>(tokio::sync::mpsc::UnboundedReceiver<M>);
@@ -14,6 +21,55 @@ impl<M> EventSource<M> {
     pub async fn option_next(&mut self) -> Option<M> {
         self.0.recv().await
     }
+
+    /// Merges two sources into one, yielding items from either as they
+    /// arrive.
+    pub fn merge(self, other: Self) -> EventSource<M>
+    where
+        M: Send + 'static,
+    {
+        use tokio_stream::StreamExt;
+
+        let (event, source) = event_channel();
+        tokio::spawn(async move {
+            let mut combined = UnboundedReceiverStream::new(self.0).merge(UnboundedReceiverStream::new(other.0));
+            while let Some(message) = combined.next().await {
+                if event.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        source
+    }
+
+    /// Adapts this source into one yielding `E`, for actors that want to
+    /// fold several message types into a shared event enum.
+    pub fn map_into<E>(self) -> EventSource<E>
+    where
+        M: Into<E> + Send + 'static,
+        E: Send + 'static,
+    {
+        use futures_util::stream::StreamExt;
+
+        let (event, source) = event_channel();
+        tokio::spawn(async move {
+            let mut stream = UnboundedReceiverStream::new(self.0);
+            while let Some(message) = stream.next().await {
+                if event.send(message.into()).is_err() {
+                    break;
+                }
+            }
+        });
+        source
+    }
+}
+
+impl<M> Stream for EventSource<M> {
+    type Item = M;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
 }
 
 #[derive(Debug, From)]
@@ -82,3 +138,282 @@ impl<T, U> SubscribeHandle<T, U> {
 }
 
 pub type SubscribeSource<T, U> = EventSource<(T, EventSender<U>)>;
+
+#[derive(Debug, From)]
+pub struct BoundedEventSource<M>(tokio::sync::mpsc::Receiver<M>);
+
+impl<M> BoundedEventSource<M> {
+    pub async fn next(&mut self) -> crate::Result<M> {
+        self.0
+            .recv()
+            .await
+            .ok_or(crate::err!("unexpected source closing"))
+    }
+
+    pub async fn option_next(&mut self) -> Option<M> {
+        self.0.recv().await
+    }
+}
+
+#[derive(Debug, From)]
+pub struct BoundedEventSender<M>(tokio::sync::mpsc::Sender<M>);
+
+impl<M> Clone for BoundedEventSender<M> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Why a channel refused a message outright, i.e. without the sender
+/// awaiting for capacity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum TrySendError {
+    #[error("channel full")]
+    Full,
+    #[error("channel closed")]
+    Closed,
+}
+
+impl<M> BoundedEventSender<M> {
+    /// Awaits until the channel has capacity, then enqueues `message`.
+    ///
+    /// Unlike [`EventSender::send`] this lets a slow consumer apply
+    /// backpressure to its producers instead of the queue growing without
+    /// bound.
+    pub async fn send(&self, message: M) -> crate::Result<()> {
+        self.0
+            .send(message)
+            .await
+            .map_err(|_| crate::err!("unexpected event channel closing"))
+    }
+
+    /// Enqueues `message` without waiting, failing instead of blocking when
+    /// the channel is full or closed.
+    pub fn try_send(&self, message: M) -> Result<(), TrySendError> {
+        self.0.try_send(message).map_err(|err| match err {
+            tokio::sync::mpsc::error::TrySendError::Full(_) => TrySendError::Full,
+            tokio::sync::mpsc::error::TrySendError::Closed(_) => TrySendError::Closed,
+        })
+    }
+}
+
+/// A bounded counterpart of [`event_channel`], backed by Tokio's
+/// semaphore-based bounded MPSC channel.
+pub fn bounded_event_channel<M>(capacity: usize) -> (BoundedEventSender<M>, BoundedEventSource<M>) {
+    let channel = tokio::sync::mpsc::channel(capacity);
+    (BoundedEventSender(channel.0), BoundedEventSource(channel.1))
+}
+
+pub type BoundedSubmitHandle<T, U> = BoundedEventSender<(T, PromiseSender<U>)>;
+
+impl<T, U> BoundedSubmitHandle<T, U> {
+    /// Awaits both queue capacity and the promise, the bounded counterpart
+    /// of [`SubmitHandle::submit`].
+    pub async fn submit(&self, op: T) -> crate::Result<U> {
+        let (result, promise) = promise_channel();
+        self.send((op, result)).await?;
+        Ok(promise.await?)
+    }
+}
+
+pub type BoundedSubmitSource<T, U> = BoundedEventSource<(T, PromiseSender<U>)>;
+
+pub type BoundedSubscribeHandle<T, U> = BoundedEventSender<(T, EventSender<U>)>;
+
+impl<T, U> BoundedSubscribeHandle<T, U> {
+    pub async fn subscribe(&self, op: T) -> crate::Result<EventSource<U>> {
+        let (event, source) = event_channel();
+        self.send((op, event)).await?;
+        Ok(source)
+    }
+}
+
+pub type BoundedSubscribeSource<T, U> = BoundedEventSource<(T, EventSender<U>)>;
+
+/// Strictly below `UdpSocket`'s 65536-byte receive buffer (see `net.rs`),
+/// leaving room for framing overhead, so a streamed chunk always fits in a
+/// single transport frame.
+pub const MAX_CHUNK_SIZE: usize = 65536 - 1024;
+
+/// One piece of a body streamed alongside a [`SubmitHandle::submit_stream`]
+/// request: an explicit sequence number plus an end-of-stream marker, so
+/// the receiving side knows when the body is complete without relying on
+/// the channel closing.
+#[derive(Debug, Clone, borsh::BorshSerialize, borsh::BorshDeserialize)]
+pub struct Chunk {
+    pub seq: u64,
+    pub data: Vec<u8>,
+    pub end: bool,
+}
+
+pub type StreamSubmitHandle<T, U> = EventSender<(T, EventSource<Chunk>, PromiseSender<U>)>;
+
+impl<T, U> StreamSubmitHandle<T, U> {
+    /// Sends `op` the way [`SubmitHandle::submit`] does, then streams
+    /// `body` alongside it as a sequence of chunks no larger than
+    /// [`MAX_CHUNK_SIZE`].
+    ///
+    /// Each chunk is held back by one until the next is known to exist, so
+    /// the final chunk actually carrying data is the one marked `end`
+    /// instead of a spurious empty chunk following it; an empty body still
+    /// flushes a single empty end-of-stream chunk.
+    pub async fn submit_stream(&self, op: T, mut body: EventSource<bytes::Bytes>) -> crate::Result<U> {
+        let (result, promise) = promise_channel();
+        let (chunk_event, chunk_source) = event_channel();
+        self.send((op, chunk_source, result))?;
+        let mut seq = 0;
+        let mut pending: Option<Chunk> = None;
+        while let Some(data) = body.option_next().await {
+            for slice in data.chunks(MAX_CHUNK_SIZE) {
+                if let Some(chunk) = pending.take() {
+                    chunk_event.send(chunk)?;
+                }
+                pending = Some(Chunk {
+                    seq,
+                    data: slice.to_vec(),
+                    end: false,
+                });
+                seq += 1;
+            }
+        }
+        let last = match pending {
+            Some(mut chunk) => {
+                chunk.end = true;
+                chunk
+            }
+            None => Chunk {
+                seq,
+                data: Vec::new(),
+                end: true,
+            },
+        };
+        chunk_event.send(last)?;
+        Ok(promise.await?)
+    }
+}
+
+pub type StreamSubmitSource<T, U> = EventSource<(T, EventSource<Chunk>, PromiseSender<U>)>;
+
+impl<T, U> StreamSubmitSource<T, U> {
+    /// The receiving counterpart of `submit_stream`: yields the op and
+    /// promise the way `SubmitSource` does, plus an `EventSource<Bytes>`
+    /// that reassembles the chunked body as it arrives.
+    pub async fn recv_stream(&mut self) -> crate::Result<(T, EventSource<bytes::Bytes>, PromiseSender<U>)>
+    where
+        T: Send + 'static,
+        U: Send + 'static,
+    {
+        let (op, mut chunks, promise) = self.next().await?;
+        let (event, body) = event_channel();
+        tokio::spawn(async move {
+            while let Some(chunk) = chunks.option_next().await {
+                let end = chunk.end;
+                if !chunk.data.is_empty() && event.send(bytes::Bytes::from(chunk.data)).is_err() {
+                    break;
+                }
+                if end {
+                    break;
+                }
+            }
+        });
+        Ok((op, body, promise))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reassembles_chunked_body_in_order() {
+        let (submit, mut source): (StreamSubmitHandle<&'static str, u32>, StreamSubmitSource<&'static str, u32>) =
+            event_channel();
+        let (body_event, body_source) = event_channel::<bytes::Bytes>();
+
+        let submitted = tokio::spawn(async move { submit.submit_stream("op", body_source).await });
+
+        body_event.send(bytes::Bytes::from_static(b"hello ")).unwrap();
+        body_event.send(bytes::Bytes::from_static(b"world")).unwrap();
+        drop(body_event);
+
+        let (op, mut received, promise) = source.recv_stream().await.unwrap();
+        assert_eq!(op, "op");
+        promise.resolve(42);
+
+        let mut reassembled = Vec::new();
+        while let Some(chunk) = received.option_next().await {
+            reassembled.extend_from_slice(&chunk);
+        }
+        assert_eq!(reassembled, b"hello world");
+        assert_eq!(submitted.await.unwrap().unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn large_body_splits_without_a_spurious_trailing_chunk() {
+        let (submit, mut source): (StreamSubmitHandle<&'static str, ()>, StreamSubmitSource<&'static str, ()>) =
+            event_channel();
+        let (body_event, body_source) = event_channel::<bytes::Bytes>();
+
+        let body = vec![7u8; MAX_CHUNK_SIZE + 1];
+        body_event.send(bytes::Bytes::from(body)).unwrap();
+        drop(body_event);
+        let submitted = tokio::spawn(async move { submit.submit_stream("op", body_source).await });
+
+        let (_op, mut chunks, promise) = source.next().await.unwrap();
+        let mut seen = Vec::new();
+        while let Some(chunk) = chunks.option_next().await {
+            let end = chunk.end;
+            seen.push(chunk);
+            if end {
+                break;
+            }
+        }
+        promise.resolve(());
+        submitted.await.unwrap().unwrap();
+
+        assert_eq!(seen.len(), 2);
+        assert!(seen[0].data.len() == MAX_CHUNK_SIZE && !seen[0].end);
+        assert!(seen[1].data.len() == 1 && seen[1].end);
+    }
+
+    #[test]
+    fn try_send_fails_without_blocking_once_the_channel_is_full() {
+        let (sender, _source) = bounded_event_channel::<u32>(1);
+        assert_eq!(sender.try_send(1), Ok(()));
+        assert_eq!(sender.try_send(2), Err(TrySendError::Full));
+    }
+
+    #[tokio::test]
+    async fn try_send_reports_closed_once_the_receiver_is_dropped() {
+        let (sender, source) = bounded_event_channel::<u32>(1);
+        drop(source);
+        assert_eq!(sender.try_send(1), Err(TrySendError::Closed));
+    }
+
+    #[tokio::test]
+    async fn send_applies_backpressure_until_the_receiver_makes_room() {
+        let (sender, mut source) = bounded_event_channel::<u32>(1);
+        sender.send(1).await.unwrap();
+
+        let sender2 = sender.clone();
+        let blocked = tokio::spawn(async move { sender2.send(2).await });
+        tokio::task::yield_now().await;
+        assert!(!blocked.is_finished(), "send should block while the channel is full");
+
+        assert_eq!(source.next().await.unwrap(), 1);
+        blocked.await.unwrap().unwrap();
+        assert_eq!(source.next().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn bounded_submit_round_trips_through_the_channel() {
+        let (submit, mut source): (BoundedSubmitHandle<&'static str, u32>, BoundedSubmitSource<&'static str, u32>) =
+            bounded_event_channel(1);
+
+        let submitted = tokio::spawn(async move { submit.submit("op").await });
+        let (op, reply) = source.next().await.unwrap();
+        assert_eq!(op, "op");
+        reply.resolve(42);
+        assert_eq!(submitted.await.unwrap().unwrap(), 42);
+    }
+}
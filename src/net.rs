@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use derive_more::From;
@@ -34,6 +34,44 @@ impl UdpSocket {
             event.send(borsh::from_slice::<M>(&buf[..len])?.into())?
         }
     }
+
+    /// A drain-aware counterpart of [`UdpSocket::listen_session`]: once
+    /// `stop` fires, we never again `await` a fresh datagram — instead we
+    /// poll the socket non-blockingly and forward only what's already
+    /// sitting in its receive buffer, for up to `grace`, instead of
+    /// discarding anything that was a few milliseconds from completion.
+    pub async fn listen_session_graceful<M, E>(
+        &self,
+        event: EventSender<E>,
+        stop: CancellationToken,
+        grace: Duration,
+    ) -> crate::Result<()>
+    where
+        M: BorshDeserialize + Into<E> + Send + 'static,
+    {
+        let mut buf = vec![0; 65536];
+        loop {
+            tokio::select! {
+                recv_from = self.0.recv_from(&mut buf) => {
+                    let (len, _remote) = recv_from?;
+                    event.send(borsh::from_slice::<M>(&buf[..len])?.into())?
+                }
+                () = stop.cancelled() => break,
+            }
+        }
+        let deadline = tokio::time::Instant::now() + grace;
+        loop {
+            if tokio::time::Instant::now() >= deadline {
+                break;
+            }
+            match self.0.try_recv_from(&mut buf) {
+                Ok((len, _remote)) => event.send(borsh::from_slice::<M>(&buf[..len])?.into())?,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,329 @@
+use std::{collections::HashSet, time::Duration};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::model::{Addr, EventSender, Message, Transport};
+
+/// Membership change a [`FullMesh`] reports to the caller-supplied
+/// [`EventSender`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerEvent {
+    PeerUp(Addr),
+    PeerDown(Addr),
+}
+
+/// On-the-wire messages `FullMesh` exchanges with its peers: a keepalive
+/// ping, a gossip of addresses the sender currently knows about, and an
+/// application payload.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub enum MeshMessage<M> {
+    Ping,
+    Gossip(Vec<Addr>),
+    Application(M),
+}
+
+struct PeerState {
+    up: bool,
+    backoff: Duration,
+}
+
+impl PeerState {
+    fn new() -> Self {
+        Self {
+            up: false,
+            backoff: FullMesh::<(), ()>::INITIAL_BACKOFF,
+        }
+    }
+}
+
+/// Maintains one live connection to every known peer over a
+/// connection-oriented `Transport`, reconnecting with exponential backoff
+/// when a link drops and gossiping newly-learned peer addresses so the
+/// mesh converges as nodes join.
+pub struct FullMesh<T, M> {
+    transport: T,
+    self_addr: Addr,
+    peers: Mutex<std::collections::HashMap<Addr, PeerState>>,
+    heartbeat: Duration,
+    _marker: std::marker::PhantomData<M>,
+}
+
+impl<T, M> FullMesh<T, M> {
+    const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+    const MAX_BACKOFF: Duration = Duration::from_secs(30);
+    const DEFAULT_HEARTBEAT: Duration = Duration::from_secs(1);
+
+    pub fn new(transport: T, self_addr: Addr, initial_peers: impl IntoIterator<Item = Addr>) -> Self {
+        Self {
+            transport,
+            self_addr,
+            peers: Mutex::new(
+                initial_peers
+                    .into_iter()
+                    .map(|addr| (addr, PeerState::new()))
+                    .collect(),
+            ),
+            heartbeat: Self::DEFAULT_HEARTBEAT,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn with_heartbeat(mut self, heartbeat: Duration) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+}
+
+impl<M, T> FullMesh<T, M>
+where
+    M: Message + Clone + Send + Sync + 'static,
+    T: Transport<MeshMessage<M>> + Clone + Send + Sync + 'static,
+{
+    /// Fans `message` out to every currently-up peer, reusing
+    /// `send_to_all`.
+    pub async fn broadcast(&self, message: M) -> crate::Result<()> {
+        let peers = self.peers.lock().await;
+        let up = peers
+            .iter()
+            .filter(|(_, state)| state.up)
+            .map(|(addr, _)| addr.clone())
+            .collect::<Vec<_>>();
+        drop(peers);
+        self.transport
+            .send_to_all(up.into_iter(), MeshMessage::Application(message))
+            .await
+    }
+
+    /// Learns `learned` as peers to dial, excluding `self.self_addr`: gossip
+    /// always includes the sender's own address (so a dialed-only node can
+    /// still join), which means our own address round-trips back to us
+    /// through any peer we're connected to. Without this filter we'd insert
+    /// ourselves into `peers` and end up dialing, pinging, and broadcasting
+    /// to ourselves forever.
+    async fn learn(&self, learned: impl IntoIterator<Item = Addr>) {
+        let mut peers = self.peers.lock().await;
+        for addr in learned {
+            if addr != self.self_addr {
+                peers.entry(addr).or_insert_with(PeerState::new);
+            }
+        }
+    }
+
+    async fn known_peers(&self) -> Vec<Addr> {
+        self.peers.lock().await.keys().cloned().collect()
+    }
+
+    async fn mark(&self, peer: &Addr, up: bool, event: &EventSender<PeerEvent>) -> crate::Result<()> {
+        let mut peers = self.peers.lock().await;
+        let Some(state) = peers.get_mut(peer) else {
+            return Ok(());
+        };
+        let changed = state.up != up;
+        state.up = up;
+        if up {
+            state.backoff = Self::INITIAL_BACKOFF;
+        }
+        drop(peers);
+        if changed {
+            event.send(if up {
+                PeerEvent::PeerUp(peer.clone())
+            } else {
+                PeerEvent::PeerDown(peer.clone())
+            })?
+        }
+        Ok(())
+    }
+
+    async fn connect_peer(&self, peer: Addr, event: EventSender<PeerEvent>, stop: CancellationToken) {
+        while !stop.is_cancelled() {
+            // Gossip includes ourselves, so a joiner dialing into an
+            // existing mesh also announces its own address to the peer it
+            // dials, not just the peers it already knows about.
+            let mut gossip = self.known_peers().await;
+            gossip.push(self.self_addr.clone());
+            let attempt = self
+                .transport
+                .send_to(peer.clone(), MeshMessage::Gossip(gossip))
+                .await;
+            if attempt.is_ok() {
+                let _ = self.mark(&peer, true, &event).await;
+                loop {
+                    tokio::select! {
+                        () = tokio::time::sleep(self.heartbeat) => {
+                            if self.transport.send_to(peer.clone(), MeshMessage::Ping).await.is_err() {
+                                break;
+                            }
+                        }
+                        () = stop.cancelled() => return,
+                    }
+                }
+                let _ = self.mark(&peer, false, &event).await;
+            }
+            let backoff = {
+                let mut peers = self.peers.lock().await;
+                let Some(state) = peers.get_mut(&peer) else {
+                    return;
+                };
+                let backoff = state.backoff;
+                state.backoff = (state.backoff * 2).min(Self::MAX_BACKOFF);
+                backoff
+            };
+            tokio::select! {
+                () = tokio::time::sleep(backoff) => {}
+                () = stop.cancelled() => return,
+            }
+        }
+    }
+
+    /// The mesh's driver task, in the actor style already used by
+    /// `submit_loop`: dials every known peer, keeps redialing on drop, and
+    /// emits `PeerUp`/`PeerDown` on `event` until `stop` fires.
+    pub async fn run(self: std::sync::Arc<Self>, event: EventSender<PeerEvent>, stop: CancellationToken) {
+        let mut spawned = HashSet::new();
+        loop {
+            let known = self.known_peers().await;
+            for peer in known {
+                if spawned.insert(peer.clone()) {
+                    let this = self.clone();
+                    let event = event.clone();
+                    let stop = stop.clone();
+                    tokio::spawn(async move { this.connect_peer(peer, event, stop).await });
+                }
+            }
+            tokio::select! {
+                () = tokio::time::sleep(self.heartbeat) => {}
+                () = stop.cancelled() => break,
+            }
+        }
+    }
+
+    /// Handles an inbound `MeshMessage`, learning gossiped peers (including
+    /// the sender itself, so a node that only ever gets dialed still joins
+    /// the mesh) and returning application payloads to the caller; `event`
+    /// only receives `PeerUp` notifications.
+    pub async fn on_message(&self, from: Addr, message: MeshMessage<M>, event: &EventSender<PeerEvent>) -> crate::Result<Option<M>> {
+        self.learn([from.clone()]).await;
+        match message {
+            MeshMessage::Ping => {
+                self.mark(&from, true, event).await?;
+                Ok(None)
+            }
+            MeshMessage::Gossip(learned) => {
+                self.mark(&from, true, event).await?;
+                self.learn(learned).await;
+                Ok(None)
+            }
+            MeshMessage::Application(message) => {
+                self.mark(&from, true, event).await?;
+                Ok(Some(message))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    /// A `Transport` double whose sends succeed or fail on command, so tests
+    /// can drive `connect_peer`'s backoff/reconnect state machine without a
+    /// real socket.
+    #[derive(Clone, Default)]
+    struct FakeTransport {
+        sent: Arc<Mutex<Vec<(Addr, MeshMessage<u8>)>>>,
+        fail: Arc<AtomicBool>,
+    }
+
+    #[async_trait::async_trait]
+    impl Transport<MeshMessage<u8>> for FakeTransport {
+        fn addr(&self) -> Addr {
+            Addr::Socket("127.0.0.1:0".parse().unwrap())
+        }
+
+        async fn send_to(&self, destination: Addr, message: MeshMessage<u8>) -> crate::Result<()>
+        where
+            MeshMessage<u8>: Message,
+        {
+            if self.fail.load(Ordering::SeqCst) {
+                crate::bail!("simulated send failure")
+            }
+            self.sent.lock().await.push((destination, message));
+            Ok(())
+        }
+
+        async fn send_to_all(&self, destinations: impl Iterator<Item = Addr> + Send, message: MeshMessage<u8>) -> crate::Result<()>
+        where
+            MeshMessage<u8>: Message,
+        {
+            for destination in destinations {
+                self.send_to(destination, message.clone()).await?;
+            }
+            Ok(())
+        }
+    }
+
+    fn addr(port: u16) -> Addr {
+        Addr::Socket(format!("127.0.0.1:{port}").parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn learn_excludes_self_and_keeps_other_peers() {
+        let mesh = FullMesh::<FakeTransport, u8>::new(FakeTransport::default(), addr(1), [addr(2)]);
+        mesh.learn([addr(1), addr(3)]).await;
+        let peers = mesh.peers.lock().await;
+        assert!(!peers.contains_key(&addr(1)), "self address must not be learned as a peer");
+        assert!(peers.contains_key(&addr(2)));
+        assert!(peers.contains_key(&addr(3)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn connect_peer_grows_backoff_on_failure_and_resets_on_success() {
+        let fail = Arc::new(AtomicBool::new(true));
+        let transport = FakeTransport {
+            fail: fail.clone(),
+            ..Default::default()
+        };
+        let peer = addr(2);
+        let mesh = Arc::new(
+            FullMesh::<FakeTransport, u8>::new(transport, addr(1), [peer.clone()]).with_heartbeat(Duration::from_secs(3600)),
+        );
+        let (event, mut events) = crate::channel::event_channel();
+        let stop = CancellationToken::new();
+
+        let driver = tokio::spawn({
+            let mesh = mesh.clone();
+            let peer = peer.clone();
+            let stop = stop.clone();
+            async move { mesh.connect_peer(peer, event, stop).await }
+        });
+
+        // Every failed dial doubles the backoff, capped at `MAX_BACKOFF`:
+        // 100ms doubled 9 times passes 30s, so 10 dials are enough to hit the cap.
+        for _ in 0..10 {
+            tokio::time::advance(FullMesh::<FakeTransport, u8>::MAX_BACKOFF).await;
+        }
+        assert_eq!(
+            mesh.peers.lock().await.get(&peer).unwrap().backoff,
+            FullMesh::<FakeTransport, u8>::MAX_BACKOFF,
+        );
+
+        // Once a dial succeeds, the peer comes up and its backoff resets.
+        fail.store(false, Ordering::SeqCst);
+        tokio::time::advance(FullMesh::<FakeTransport, u8>::MAX_BACKOFF).await;
+        assert_eq!(events.option_next().await, Some(PeerEvent::PeerUp(peer.clone())));
+        assert_eq!(
+            mesh.peers.lock().await.get(&peer).unwrap().backoff,
+            FullMesh::<FakeTransport, u8>::INITIAL_BACKOFF,
+        );
+
+        stop.cancel();
+        driver.abort();
+    }
+}
@@ -0,0 +1,424 @@
+use std::{collections::HashMap, sync::Arc};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey};
+
+use crate::model::{Addr, EventSender, EventSource, Message, Transport};
+
+/// What travels on the wire once a [`SecureTransport`] is in the picture:
+/// either the one-shot key-exchange announcement or a sealed application
+/// frame.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub enum SecureFrame {
+    Handshake {
+        identity: [u8; 32],
+        ephemeral: [u8; 32],
+        signature: [u8; 64],
+    },
+    Sealed {
+        nonce: [u8; 12],
+        ciphertext: Vec<u8>,
+    },
+}
+
+struct Session {
+    cipher: ChaCha20Poly1305,
+    /// `0` or `1`, chosen from the ordering of the two identity keys so the
+    /// two ends of a session never share a nonce prefix under the same key.
+    direction: u8,
+    send_nonce: u64,
+    recv_nonce: u64,
+}
+
+impl Session {
+    /// `true` if `counter` is newer than every nonce already accepted from
+    /// this peer, in which case it also becomes the new floor; `false` if
+    /// `counter` is below that floor (a replayed nonce, or an older one that
+    /// was skipped), leaving the floor unchanged.
+    fn accept_nonce(&mut self, counter: u64) -> bool {
+        if counter < self.recv_nonce {
+            return false;
+        }
+        self.recv_nonce = counter + 1;
+        true
+    }
+}
+
+/// A handshake we initiated and are still waiting on the peer's matching
+/// reply for, before a [`Session`] can be installed.
+struct PendingHandshake {
+    our_ephemeral: EphemeralSecret,
+    ready: Arc<tokio::sync::Notify>,
+}
+
+enum SessionState {
+    Pending(PendingHandshake),
+    Established(Session),
+}
+
+/// Maps a peer [`Addr`] to its long-lived ed25519 identity, i.e. the set of
+/// peers a node is willing to talk to.
+pub trait PeerVerifier: Send + Sync {
+    fn verify(&self, peer: Addr) -> Option<VerifyingKey>;
+}
+
+impl<F: Fn(Addr) -> Option<VerifyingKey> + Send + Sync> PeerVerifier for F {
+    fn verify(&self, peer: Addr) -> Option<VerifyingKey> {
+        self(peer)
+    }
+}
+
+fn derive_cipher(shared_secret: &[u8], ours: &X25519PublicKey, theirs: &X25519PublicKey) -> ChaCha20Poly1305 {
+    let (low, high) = if ours.as_bytes() < theirs.as_bytes() {
+        (ours, theirs)
+    } else {
+        (theirs, ours)
+    };
+    let hkdf = Hkdf::<Sha256>::new(None, shared_secret);
+    let mut key = [0; 32];
+    hkdf.expand(&[low.as_bytes().as_slice(), high.as_bytes().as_slice()].concat(), &mut key)
+        .expect("32 bytes is a valid HKDF output length");
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+/// Picks a distinct nonce-prefix byte for each end of a session, so the two
+/// peers sharing one HKDF-derived key never emit the same nonce: the side
+/// with the lexicographically smaller identity key sends under prefix `0`,
+/// the other under `1`.
+fn direction_of(our_identity: &[u8; 32], their_identity: &[u8; 32]) -> u8 {
+    if our_identity < their_identity {
+        0
+    } else {
+        1
+    }
+}
+
+fn nonce_bytes(direction: u8, counter: u64) -> [u8; 12] {
+    let mut nonce = [0; 12];
+    nonce[0] = direction;
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    nonce
+}
+
+/// Wraps a connection-oriented [`Transport`] to provide mutual
+/// authentication and encryption, so protocols built on top don't each
+/// reimplement a handshake.
+///
+/// On first contact with a peer, `our_keypair` signs an ephemeral X25519
+/// public key and sends it alongside our long-lived identity; the peer
+/// replies in kind (or, if both sides dial at once, both replies land and
+/// agree on the same secret since Diffie-Hellman is symmetric). Once both
+/// ephemeral keys are known, the shared secret (plus both public keys) is
+/// fed through HKDF to derive a session key, and every subsequent frame is
+/// sealed with ChaCha20-Poly1305 under a per-direction incrementing nonce
+/// (the two ends pick distinct prefixes from their identity key ordering,
+/// so they never reuse each other's nonces under the shared key). A nonce
+/// that doesn't strictly increase is treated as a replay and rejected.
+pub struct SecureTransport<T> {
+    inner: T,
+    identity: Arc<SigningKey>,
+    peer_verifier: Arc<dyn PeerVerifier>,
+    sessions: Arc<Mutex<HashMap<Addr, SessionState>>>,
+}
+
+impl<T: Clone> Clone for SecureTransport<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            identity: self.identity.clone(),
+            peer_verifier: self.peer_verifier.clone(),
+            sessions: self.sessions.clone(),
+        }
+    }
+}
+
+impl<T> SecureTransport<T> {
+    pub fn new(inner: T, our_keypair: SigningKey, peer_verifier: impl PeerVerifier + 'static) -> Self {
+        Self {
+            inner,
+            identity: Arc::new(our_keypair),
+            peer_verifier: Arc::new(peer_verifier),
+            sessions: Default::default(),
+        }
+    }
+}
+
+impl<T> SecureTransport<T>
+where
+    T: Transport<SecureFrame>,
+{
+    fn sign_ephemeral(&self, ephemeral: &X25519PublicKey) -> SecureFrame {
+        SecureFrame::Handshake {
+            identity: self.identity.verifying_key().to_bytes(),
+            ephemeral: ephemeral.to_bytes(),
+            signature: self.identity.sign(ephemeral.as_bytes()).to_bytes(),
+        }
+    }
+
+    /// Blocks the first `send_to`/`send_to_all` to a peer until a session
+    /// is established, initiating the handshake if nobody has yet.
+    async fn ensure_session(&self, peer: Addr) -> crate::Result<()> {
+        loop {
+            let mut sessions = self.sessions.lock().await;
+            match sessions.get(&peer) {
+                Some(SessionState::Established(_)) => return Ok(()),
+                Some(SessionState::Pending(pending)) => {
+                    let ready = pending.ready.clone();
+                    drop(sessions);
+                    ready.notified().await;
+                }
+                None => {
+                    if self.peer_verifier.verify(peer.clone()).is_none() {
+                        crate::bail!("no authorized identity for peer {peer:?}")
+                    }
+                    let our_ephemeral = EphemeralSecret::random();
+                    let our_ephemeral_public = X25519PublicKey::from(&our_ephemeral);
+                    let frame = self.sign_ephemeral(&our_ephemeral_public);
+                    let ready = Arc::new(tokio::sync::Notify::new());
+                    sessions.insert(
+                        peer.clone(),
+                        SessionState::Pending(PendingHandshake {
+                            our_ephemeral,
+                            ready: ready.clone(),
+                        }),
+                    );
+                    drop(sessions);
+                    // Register interest in the notification *before*
+                    // sending the handshake frame: `on_handshake` can run
+                    // (and call `notify_waiters`) as soon as the peer's
+                    // reply lands, which can race ahead of `send_to`
+                    // finishing its own await points. `notify_waiters` only
+                    // wakes tasks already polling `notified()` — it isn't
+                    // queued like `notify_one` — so enabling the future
+                    // first is what makes the notification impossible to
+                    // miss, even though we haven't started waiting on it.
+                    let notified = ready.notified();
+                    tokio::pin!(notified);
+                    notified.as_mut().enable();
+                    if let Err(err) = self.inner.send_to(peer.clone(), frame).await {
+                        // Don't leave the `Pending` entry behind on a failed
+                        // dial: every future `ensure_session`/`send_to` for
+                        // this peer would otherwise await a notification
+                        // that's never coming and hang forever instead of
+                        // retrying the handshake.
+                        self.sessions.lock().await.remove(&peer);
+                        return Err(err);
+                    }
+                    notified.await;
+                }
+            }
+        }
+    }
+
+    /// Handles a handshake frame off the wire, whichever side it came from.
+    ///
+    /// If we were already waiting on a reply to our own handshake with this
+    /// peer, this completes the Diffie-Hellman exchange using the ephemeral
+    /// secret we kept around and installs the session. Otherwise this is the
+    /// peer initiating contact with us: we mint our own ephemeral, derive
+    /// the (symmetric) shared secret right away, install the session, and
+    /// echo a handshake back so the peer can complete its side too.
+    pub async fn on_handshake(&self, peer: Addr, identity: [u8; 32], ephemeral: [u8; 32], signature: [u8; 64]) -> crate::Result<()> {
+        let Some(peer_key) = self.peer_verifier.verify(peer.clone()) else {
+            crate::bail!("no authorized identity for peer {peer:?}")
+        };
+        if peer_key.to_bytes() != identity {
+            crate::bail!("peer {peer:?} presented an unexpected identity")
+        }
+        let theirs = X25519PublicKey::from(ephemeral);
+        peer_key
+            .verify(theirs.as_bytes(), &Signature::from_bytes(&signature))
+            .map_err(|_| crate::err!("invalid handshake signature from {peer:?}"))?;
+
+        let mut sessions = self.sessions.lock().await;
+        let pending = match sessions.remove(&peer) {
+            Some(SessionState::Established(session)) => {
+                sessions.insert(peer, SessionState::Established(session));
+                return Ok(());
+            }
+            Some(SessionState::Pending(pending)) => Some(pending),
+            None => None,
+        };
+
+        let our_identity = self.identity.verifying_key().to_bytes();
+        let direction = direction_of(&our_identity, &identity);
+        let reply = match pending {
+            Some(pending) => {
+                let ours = X25519PublicKey::from(&pending.our_ephemeral);
+                let shared_secret = pending.our_ephemeral.diffie_hellman(&theirs);
+                let cipher = derive_cipher(shared_secret.as_bytes(), &ours, &theirs);
+                sessions.insert(
+                    peer.clone(),
+                    SessionState::Established(Session {
+                        cipher,
+                        direction,
+                        send_nonce: 0,
+                        recv_nonce: 0,
+                    }),
+                );
+                pending.ready.notify_waiters();
+                None
+            }
+            None => {
+                let our_ephemeral = EphemeralSecret::random();
+                let our_ephemeral_public = X25519PublicKey::from(&our_ephemeral);
+                let shared_secret = our_ephemeral.diffie_hellman(&theirs);
+                let cipher = derive_cipher(shared_secret.as_bytes(), &our_ephemeral_public, &theirs);
+                sessions.insert(
+                    peer.clone(),
+                    SessionState::Established(Session {
+                        cipher,
+                        direction,
+                        send_nonce: 0,
+                        recv_nonce: 0,
+                    }),
+                );
+                Some(self.sign_ephemeral(&our_ephemeral_public))
+            }
+        };
+        drop(sessions);
+        if let Some(reply) = reply {
+            self.inner.send_to(peer, reply).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn open(&self, peer: Addr, frame: SecureFrame) -> crate::Result<Vec<u8>> {
+        let SecureFrame::Sealed { nonce, ciphertext } = frame else {
+            crate::bail!("expected a sealed frame from {peer:?}")
+        };
+        let mut sessions = self.sessions.lock().await;
+        let Some(SessionState::Established(session)) = sessions.get_mut(&peer) else {
+            crate::bail!("no established session with {peer:?}")
+        };
+        let counter = u64::from_be_bytes(nonce[4..].try_into().expect("8 bytes"));
+        if !session.accept_nonce(counter) {
+            crate::bail!("rejecting replayed frame from {peer:?}")
+        }
+        session
+            .cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| crate::err!("failed to open sealed frame from {peer:?}"))
+    }
+
+    /// The listen-side counterpart of `SecureTransport`: reads `SecureFrame`s
+    /// already demultiplexed by peer (e.g. one per `TcpSocket` connection),
+    /// completing handshakes via `on_handshake` and forwarding decrypted
+    /// `Sealed` frames to `event`, so inbound secure traffic is driven the
+    /// same way `UdpSocket`/`TcpSocket::listen_session` drive plaintext
+    /// traffic.
+    pub async fn listen_session<M, E>(
+        &self,
+        peer: Addr,
+        mut frames: EventSource<SecureFrame>,
+        event: EventSender<E>,
+        stop: CancellationToken,
+    ) -> crate::Result<()>
+    where
+        M: BorshDeserialize + Into<E> + Send + 'static,
+    {
+        loop {
+            let frame = tokio::select! {
+                frame = frames.option_next() => match frame {
+                    Some(frame) => frame,
+                    None => break Ok(()),
+                },
+                () = stop.cancelled() => break Ok(()),
+            };
+            match frame {
+                SecureFrame::Handshake {
+                    identity,
+                    ephemeral,
+                    signature,
+                } => self.on_handshake(peer.clone(), identity, ephemeral, signature).await?,
+                sealed @ SecureFrame::Sealed { .. } => {
+                    let plaintext = self.open(peer.clone(), sealed).await?;
+                    event.send(borsh::from_slice::<M>(&plaintext)?.into())?
+                }
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<M, T> Transport<M> for SecureTransport<T>
+where
+    M: Message + Clone + Send + 'static,
+    T: Transport<SecureFrame> + Send + Sync + 'static,
+{
+    fn addr(&self) -> Addr {
+        self.inner.addr()
+    }
+
+    async fn send_to(&self, destination: Addr, message: M) -> crate::Result<()>
+    where
+        M: Message,
+    {
+        self.ensure_session(destination.clone()).await?;
+        let mut sessions = self.sessions.lock().await;
+        let Some(SessionState::Established(session)) = sessions.get_mut(&destination) else {
+            crate::bail!("no established session with {destination:?}")
+        };
+        let nonce = nonce_bytes(session.direction, session.send_nonce);
+        session.send_nonce += 1;
+        let plaintext = borsh::to_vec(&message)?;
+        let ciphertext = session
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|_| crate::err!("failed to seal frame for {destination:?}"))?;
+        drop(sessions);
+        self.inner
+            .send_to(destination, SecureFrame::Sealed { nonce, ciphertext })
+            .await
+    }
+
+    async fn send_to_all(&self, destinations: impl Iterator<Item = Addr> + Send, message: M) -> crate::Result<()>
+    where
+        M: Message,
+    {
+        for destination in destinations {
+            self.send_to(destination, message.clone()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        Session {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&[0; 32])),
+            direction: 0,
+            send_nonce: 0,
+            recv_nonce: 0,
+        }
+    }
+
+    #[test]
+    fn accepts_strictly_increasing_nonces() {
+        let mut session = session();
+        assert!(session.accept_nonce(0));
+        assert!(session.accept_nonce(1));
+        assert!(session.accept_nonce(5));
+    }
+
+    #[test]
+    fn rejects_replayed_and_stale_nonces() {
+        let mut session = session();
+        assert!(session.accept_nonce(3));
+        assert!(!session.accept_nonce(3));
+        assert!(!session.accept_nonce(1));
+        assert!(session.accept_nonce(4));
+    }
+}
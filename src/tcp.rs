@@ -0,0 +1,307 @@
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use derive_more::From;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{tcp::OwnedWriteHalf, TcpListener, TcpStream},
+    sync::Mutex,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::model::{Addr, EventSender, Message, Transport};
+
+/// Caps the length prefix a peer is allowed to advertise, so a malformed or
+/// hostile frame can't force an allocation of up to 4 GiB before we've even
+/// validated the payload.
+const MAX_FRAME_SIZE: u32 = 16 * 1024 * 1024;
+
+async fn write_frame(stream: &mut OwnedWriteHalf, buf: &[u8]) -> crate::Result<()> {
+    stream.write_u32(buf.len() as u32).await?;
+    stream.write_all(buf).await?;
+    Ok(())
+}
+
+async fn read_frame(stream: &mut (impl AsyncReadExt + Unpin)) -> crate::Result<Vec<u8>> {
+    let len = stream.read_u32().await?;
+    if len > MAX_FRAME_SIZE {
+        crate::bail!("frame of {len} bytes exceeds the {MAX_FRAME_SIZE}-byte limit")
+    }
+    let mut buf = vec![0; len as usize];
+    stream.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+#[derive(Debug, Clone, From)]
+pub struct TcpSocket(Arc<TcpListener>);
+
+impl TcpSocket {
+    pub async fn bind(addr: Addr) -> crate::Result<Self> {
+        let Addr::Socket(addr) = addr else {
+            crate::bail!("unsupported {addr:?}")
+        };
+        Ok(Self(Arc::new(TcpListener::bind(addr).await?)))
+    }
+
+    pub async fn listen_session<M, E>(
+        &self,
+        event: EventSender<E>,
+        stop: CancellationToken,
+    ) -> crate::Result<()>
+    where
+        M: BorshDeserialize + Into<E> + Send + 'static,
+        E: Send + 'static,
+    {
+        loop {
+            let (stream, _remote) = tokio::select! {
+                accept = self.0.accept() => accept?,
+                () = stop.cancelled() => break Ok(()),
+            };
+            let event = event.clone();
+            let stop = stop.clone();
+            tokio::spawn(async move {
+                if let Err(err) = Self::connection_session::<M, E>(stream, event, stop).await {
+                    eprintln!("tcp connection session exit with `{err}`")
+                }
+            });
+        }
+    }
+
+    async fn connection_session<M, E>(
+        mut stream: TcpStream,
+        event: EventSender<E>,
+        stop: CancellationToken,
+    ) -> crate::Result<()>
+    where
+        M: BorshDeserialize + Into<E> + Send + 'static,
+    {
+        loop {
+            let buf = tokio::select! {
+                buf = read_frame(&mut stream) => buf?,
+                () = stop.cancelled() => break Ok(()),
+            };
+            event.send(borsh::from_slice::<M>(&buf)?.into())?
+        }
+    }
+
+    /// Keeps forwarding frames from an already-open connection after `stop`
+    /// fires, for up to `grace`, instead of dropping whatever is already in
+    /// flight.
+    ///
+    /// The drain phase never calls `listener.accept()` again, but a frame
+    /// already in flight on this connection is awaited to completion (up to
+    /// `grace`) rather than polled once and discarded: `read_frame` reads
+    /// the length prefix and the payload in two sequential `.await`s, so
+    /// polling it via `now_or_never` can consume the length prefix off the
+    /// socket and then drop the future before the payload arrives,
+    /// desynchronizing the framing for the rest of the connection. Racing
+    /// the same in-flight `read_frame` against a deadline sleep avoids that.
+    async fn connection_session_graceful<M, E>(
+        mut stream: TcpStream,
+        event: EventSender<E>,
+        stop: CancellationToken,
+        grace: Duration,
+    ) -> crate::Result<()>
+    where
+        M: BorshDeserialize + Into<E> + Send + 'static,
+    {
+        let mut deadline = None;
+        loop {
+            let read = read_frame(&mut stream);
+            tokio::pin!(read);
+            let buf = loop {
+                tokio::select! {
+                    buf = &mut read => break buf?,
+                    () = stop.cancelled(), if deadline.is_none() => {
+                        deadline = Some(tokio::time::Instant::now() + grace)
+                    }
+                    () = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)), if deadline.is_some() => return Ok(()),
+                }
+            };
+            event.send(borsh::from_slice::<M>(&buf)?.into())?
+        }
+    }
+
+    pub fn into_transport<M>(self) -> TcpTransport<M> {
+        self.into()
+    }
+
+    /// A drain-aware counterpart of [`TcpSocket::listen_session`]: once
+    /// `stop` fires, no further connections are accepted, but sessions
+    /// already spawned for existing connections keep forwarding frames for
+    /// up to `grace` before being dropped.
+    pub async fn listen_session_graceful<M, E>(
+        &self,
+        event: EventSender<E>,
+        stop: CancellationToken,
+        grace: Duration,
+    ) -> crate::Result<()>
+    where
+        M: BorshDeserialize + Into<E> + Send + 'static,
+        E: Send + 'static,
+    {
+        loop {
+            let (stream, _remote) = tokio::select! {
+                accept = self.0.accept() => accept?,
+                () = stop.cancelled() => break,
+            };
+            let event = event.clone();
+            let stop = stop.clone();
+            tokio::spawn(async move {
+                if let Err(err) = Self::connection_session_graceful::<M, E>(stream, event, stop, grace).await {
+                    eprintln!("tcp connection session exit with `{err}`")
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct TcpTransport<M> {
+    listener: Arc<TcpListener>,
+    connections: Arc<Mutex<HashMap<SocketAddr, Arc<Mutex<Option<OwnedWriteHalf>>>>>>,
+    _marker: std::marker::PhantomData<M>,
+}
+
+impl<M> From<TcpSocket> for TcpTransport<M> {
+    fn from(TcpSocket(listener): TcpSocket) -> Self {
+        Self {
+            listener,
+            connections: Default::default(),
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<M> Clone for TcpTransport<M> {
+    fn clone(&self) -> Self {
+        Self {
+            listener: self.listener.clone(),
+            connections: self.connections.clone(),
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<M> TcpTransport<M> {
+    /// Sends a single framed `buf` to `destination`, reusing a pooled
+    /// connection when one is already open and lazily dialing one
+    /// otherwise.
+    ///
+    /// Only the per-destination slot is locked for the connect/write, so a
+    /// slow or still-dialing peer can't block sends to every other
+    /// destination; the outer map lock is held just long enough to look up
+    /// or insert that slot.
+    async fn send_frame(&self, destination: SocketAddr, buf: &[u8]) -> crate::Result<()> {
+        let slot = self
+            .connections
+            .lock()
+            .await
+            .entry(destination)
+            .or_insert_with(|| Arc::new(Mutex::new(None)))
+            .clone();
+        let mut slot = slot.lock().await;
+        if let Some(write_half) = slot.as_mut() {
+            if write_frame(write_half, buf).await.is_ok() {
+                return Ok(());
+            }
+            *slot = None;
+        }
+        let (_read_half, mut write_half) = TcpStream::connect(destination).await?.into_split();
+        write_frame(&mut write_half, buf).await?;
+        *slot = Some(write_half);
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<M, N> Transport<M> for TcpTransport<N>
+where
+    M: Into<N> + Send + 'static,
+    N: BorshSerialize + Send + Sync + 'static,
+{
+    fn addr(&self) -> Addr {
+        Addr::Socket(self.listener.local_addr().expect("retrievable local address"))
+    }
+
+    async fn send_to(&self, destination: Addr, message: M) -> crate::Result<()>
+    where
+        M: Message,
+    {
+        let Addr::Socket(destination) = destination else {
+            crate::bail!("unsupported destination kind {destination:?}")
+        };
+        let buf = borsh::to_vec(&message.into())?;
+        self.send_frame(destination, &buf).await
+    }
+
+    async fn send_to_all(
+        &self,
+        destinations: impl Iterator<Item = Addr> + Send,
+        message: M,
+    ) -> crate::Result<()>
+    where
+        M: Message,
+    {
+        let buf = borsh::to_vec(&message.into())?;
+        for destination in destinations {
+            let Addr::Socket(destination) = destination else {
+                crate::bail!("unsupported destination kind {destination:?}")
+            };
+            self.send_frame(destination, &buf).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn read_frame_rejects_a_length_prefix_over_the_limit() {
+        let (mut client, mut server) = tokio::io::duplex(16);
+        tokio::spawn(async move {
+            let _ = client.write_u32(MAX_FRAME_SIZE + 1).await;
+        });
+        let err = read_frame(&mut server).await.unwrap_err();
+        assert!(err.to_string().contains("exceeds"));
+    }
+
+    #[tokio::test]
+    async fn send_frame_reuses_the_pooled_connection_and_redials_after_it_drops() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let accepts = Arc::new(AtomicUsize::new(0));
+        tokio::spawn({
+            let accepts = accepts.clone();
+            async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    accepts.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(async move {
+                        let mut buf = [0; 64];
+                        while !matches!(stream.read(&mut buf).await, Ok(0) | Err(_)) {}
+                    });
+                }
+            }
+        });
+
+        let socket = TcpSocket::bind(Addr::Socket("127.0.0.1:0".parse().unwrap())).await.unwrap();
+        let transport: TcpTransport<()> = socket.into_transport();
+
+        transport.send_frame(server_addr, b"one").await.unwrap();
+        transport.send_frame(server_addr, b"two").await.unwrap();
+        // Let the accept loop run so it has counted every connection dialed so far.
+        tokio::task::yield_now().await;
+        assert_eq!(accepts.load(Ordering::SeqCst), 1, "second send should reuse the pooled connection");
+
+        // Simulate the pooled connection having died, forcing a redial.
+        transport.connections.lock().await.get(&server_addr).unwrap().lock().await.take();
+        transport.send_frame(server_addr, b"three").await.unwrap();
+        tokio::task::yield_now().await;
+        assert_eq!(accepts.load(Ordering::SeqCst), 2, "send after a dropped connection should redial");
+    }
+}